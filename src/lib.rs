@@ -14,6 +14,52 @@ macro_rules! log {
     }
 }
 
+/// Number of bits packed into each storage word.
+const BITS_PER_WORD: u32 = 32;
+
+/// Append an RLE run (`<count?><tag>`, count omitted when 1) to `out`.
+fn push_run(out: &mut String, len: u32, tag: char) {
+    if len > 1 {
+        out.push_str(&len.to_string());
+    }
+    out.push(tag);
+}
+
+/// Advance a xorshift64 PRNG state by one step.
+fn xorshift64(mut state: u64) -> u64 {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    state
+}
+
+/// Milliseconds since the page's time origin, via `performance.now()`.
+pub fn now() -> f64 {
+    web_sys::window()
+        .expect("no global `window` exists")
+        .performance()
+        .expect("`window` should have a `Performance`")
+        .now()
+}
+
+/// RAII wrapper around `console.time`/`console.timeEnd`.
+pub struct Timer<'a> {
+    name: &'a str,
+}
+
+impl<'a> Timer<'a> {
+    pub fn new(name: &'a str) -> Timer<'a> {
+        web_sys::console::time_with_label(name);
+        Timer { name }
+    }
+}
+
+impl<'a> Drop for Timer<'a> {
+    fn drop(&mut self) {
+        web_sys::console::time_end_with_label(self.name);
+    }
+}
+
 impl Universe {
     fn get_index(&self, row: u32, column: u32) -> usize {
         (row * self.width + column) as usize
@@ -27,6 +73,46 @@ impl Universe {
         index as u32 % self.width
     }
 
+    fn cell_count(&self) -> u32 {
+        self.width * self.height
+    }
+
+    /// Number of `u32` words needed to pack `count` bits.
+    fn word_count(count: u32) -> usize {
+        count.div_ceil(BITS_PER_WORD) as usize
+    }
+
+    /// Read the cell at `idx` out of the packed bit array.
+    fn get_bit(&self, idx: usize) -> bool {
+        let word = self.cells[idx / BITS_PER_WORD as usize];
+        (word >> (idx as u32 % BITS_PER_WORD)) & 1 == 1
+    }
+
+    /// Write the cell at `idx` into the packed bit array, resetting its age.
+    fn set_bit(&mut self, idx: usize, alive: bool) {
+        Universe::write_bit(&mut self.cells, idx, alive);
+        self.ages[idx] = 0;
+    }
+
+    /// Write the cell at `idx` into an arbitrary packed bit array.
+    fn write_bit(words: &mut [u32], idx: usize, alive: bool) {
+        let bit = 1 << (idx as u32 % BITS_PER_WORD);
+        let word = &mut words[idx / BITS_PER_WORD as usize];
+        if alive {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+    }
+
+    /// Set the cell at `(row, col)` to `alive`, ignoring out-of-bounds cells.
+    fn set_cell_clamped(&mut self, row: u32, col: u32, alive: bool) {
+        if row < self.height && col < self.width {
+            let idx = self.get_index(row, col);
+            self.set_bit(idx, alive);
+        }
+    }
+
     /// Generate spaceship based on clicked index
     fn gen_spaceship(&mut self, row: u32, col: u32) {
         self.set_cells(&[
@@ -38,9 +124,19 @@ impl Universe {
         ]);
     }
 
-    /// Get the dead and alive values of the entire universe.
-    pub fn get_cells(&self) -> &[Cell] {
-        &self.cells
+    /// Unpack the dead and alive values of the entire universe. Allocates
+    /// and copies the whole grid on every call; prefer `cells()` +
+    /// `cells_len_words()` on the hot path.
+    pub fn get_cells(&self) -> Vec<Cell> {
+        (0..self.cell_count() as usize)
+            .map(|idx| {
+                if self.get_bit(idx) {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                }
+            })
+            .collect()
     }
 
     /// Set cells to be alive in a universe by passing the row and column
@@ -48,7 +144,7 @@ impl Universe {
     pub fn set_cells(&mut self, cells: &[(u32, u32)]) {
         for (row, col) in cells.iter().cloned() {
             let idx = self.get_index(row, col);
-            self.cells[idx] = Cell::Alive;
+            self.set_bit(idx, true);
         }
     }
 
@@ -63,7 +159,7 @@ impl Universe {
                 let neighbor_row = (row + delta_row) % self.height;
                 let neighbor_col = (column + delta_col) % self.width;
                 let idx = self.get_index(neighbor_row, neighbor_col);
-                count += self.cells[idx] as u8;
+                count += self.get_bit(idx) as u8;
             }
         }
         count
@@ -81,18 +177,18 @@ impl Universe {
             for col in 0..13 {
                 let idx = (index as u32 + row * self.width + col) as usize;
                 if row == 1 || row == 6 || row == 11 {
-                    self.cells[idx].kill();
+                    self.set_bit(idx, false);
                 } else if row == 0 || row == 5 || row == 7 || row == 12 {
                     if col >= 2 && col <= 4 || col >= 8 && col <= 10 {
-                        self.cells[idx].birth();
+                        self.set_bit(idx, true);
                     } else {
-                        self.cells[idx].kill();
+                        self.set_bit(idx, false);
                     }
                 } else {
                     if col == 0 || col == 5 || col == 7 || col == 12 {
-                        self.cells[idx].birth();
+                        self.set_bit(idx, true);
                     } else {
-                        self.cells[idx].kill();
+                        self.set_bit(idx, false);
                     }
                 }
             }
@@ -108,28 +204,24 @@ pub enum Cell {
     Alive = 1,
 }
 
-impl Cell {
-    fn toggle(&mut self) {
-        *self = match *self {
-            Cell::Dead => Cell::Alive,
-            Cell::Alive => Cell::Dead,
-        };
-    }
-
-    fn birth(&mut self) {
-        *self = Cell::Alive;
-    }
-
-    fn kill(&mut self) {
-        *self = Cell::Dead;
-    }
-}
-
 #[wasm_bindgen]
 pub struct Universe {
     width: u32,
     height: u32,
-    cells: Vec<Cell>,
+    // One bit per cell, LSB-first per word, row-major (`row * width + col`).
+    cells: Vec<u32>,
+    // Back buffer `tick` writes into, then swaps with `cells`.
+    next_cells: Vec<u32>,
+    // Generations since each cell last died, one byte per cell, capped at
+    // 255 and reset to 0 on birth.
+    ages: Vec<u8>,
+    // Running total/count of `tick_timed` durations, in microseconds.
+    total_tick_micros: f64,
+    tick_count: u32,
+    // Bitmasks: bit N set means N live neighbors triggers that transition.
+    // See `set_rule`.
+    birth_rules: u16,
+    survival_rules: u16,
 }
 
 #[wasm_bindgen]
@@ -140,21 +232,26 @@ impl Universe {
         let width = 64;
         let height = 64;
 
-        let cells = (0..width * height)
-            .map(|i| {
-                if i % 2 == 0 || i % 7 == 0 {
-                    Cell::Alive
-                } else {
-                    Cell::Dead
-                }
-            })
-            .collect();
-
-        Universe {
+        let mut universe = Universe {
             width,
             height,
-            cells,
+            cells: vec![0; Universe::word_count(width * height)],
+            next_cells: vec![0; Universe::word_count(width * height)],
+            ages: vec![0; (width * height) as usize],
+            total_tick_micros: 0.0,
+            tick_count: 0,
+            birth_rules: 0,
+            survival_rules: 0,
+        };
+        universe.set_rule("B3/S23");
+
+        for i in 0..width * height {
+            if i % 2 == 0 || i % 7 == 0 {
+                universe.set_bit(i as usize, true);
+            }
         }
+
+        universe
     }
 
     pub fn width(&self) -> u32 {
@@ -167,26 +264,87 @@ impl Universe {
 
     pub fn set_width(&mut self, width: u32) {
         self.width = width;
-        self.cells = (0..width * self.height).map(|_| Cell::Dead).collect();
+        let words = Universe::word_count(width * self.height);
+        self.cells = vec![0; words];
+        self.next_cells = vec![0; words];
+        self.ages = vec![0; (width * self.height) as usize];
     }
 
     pub fn set_height(&mut self, height: u32) {
         self.height = height;
-        self.cells = (0..self.width * height).map(|_| Cell::Dead).collect();
+        let words = Universe::word_count(self.width * height);
+        self.cells = vec![0; words];
+        self.next_cells = vec![0; words];
+        self.ages = vec![0; (self.width * height) as usize];
     }
 
     /// Clear the universe (all cells dead)
     pub fn kill_all(&mut self) {
-        self.cells = (0..self.width * self.height).map(|_| Cell::Dead).collect();
+        for word in self.cells.iter_mut() {
+            *word = 0;
+        }
+        for age in self.ages.iter_mut() {
+            *age = 0;
+        }
     }
 
-    pub fn cells(&self) -> *const Cell {
+    /// Pointer to the packed cell words (see `cells` field doc).
+    pub fn cells(&self) -> *const u32 {
         self.cells.as_ptr()
     }
 
+    /// Number of `u32` words backing `cells()`.
+    pub fn cells_len_words(&self) -> u32 {
+        self.cells.len() as u32
+    }
+
+    /// Parse a `B<digits>/S<digits>` rule string (e.g. `B36/S23`, `B2/S`)
+    /// into `birth_rules`/`survival_rules`.
+    pub fn set_rule(&mut self, rule: &str) {
+        let mut birth_mask: u16 = 0;
+        let mut survival_mask: u16 = 0;
+        let mut in_survival = false;
+
+        for ch in rule.chars() {
+            match ch {
+                'B' | 'b' => in_survival = false,
+                'S' | 's' => in_survival = true,
+                '0'..='9' => {
+                    let bit: u16 = 1 << ch.to_digit(10).unwrap();
+                    if in_survival {
+                        survival_mask |= bit;
+                    } else {
+                        birth_mask |= bit;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.birth_rules = birth_mask;
+        self.survival_rules = survival_mask;
+    }
+
+    /// Fill the grid from a seeded xorshift64 PRNG. `density` is the
+    /// fraction of cells born alive, in `[0.0, 1.0]`.
+    pub fn randomize(&mut self, seed: u64, density: f64) {
+        let mut state = seed | 1;
+        for idx in 0..self.cell_count() as usize {
+            state = xorshift64(state);
+            let alive = (state as f64 / u64::MAX as f64) < density;
+            self.set_bit(idx, alive);
+        }
+    }
+
+    /// Pointer to one age byte per cell (see `ages` field doc).
+    pub fn ages(&self) -> *const u8 {
+        self.ages.as_ptr()
+    }
+
     pub fn toggle_cell(&mut self, row: u32, column: u32) {
         let idx = self.get_index(row, column);
-        self.cells[idx].toggle();
+        let alive = !self.get_bit(idx);
+        self.set_bit(idx, alive);
     }
 
     pub fn add_pulsar(&mut self, row: u32, column: u32) {
@@ -198,47 +356,171 @@ impl Universe {
         self.gen_spaceship(row, column);
     }
 
-    pub fn tick(&mut self) {
-        let mut next = self.cells.clone();
+    /// Decode a Life RLE pattern and place its top-left corner at
+    /// `(row, col)`. Lines starting with `#` are ignored; the `x = .., y =
+    /// ..` header's optional `rule = ..` is applied via `set_rule`. Cells
+    /// falling outside the current grid are silently dropped.
+    pub fn from_rle(&mut self, row: u32, col: u32, rle: &str) {
+        let mut cur_row = row;
+        let mut cur_col = col;
+        let mut count: u32 = 0;
+
+        'lines: for line in rle.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('x') {
+                if let Some(rule) = line.split("rule").nth(1).and_then(|s| s.split('=').nth(1)) {
+                    self.set_rule(rule.trim());
+                }
+                continue;
+            }
+
+            for ch in line.chars() {
+                match ch {
+                    '0'..='9' => count = count * 10 + ch.to_digit(10).unwrap(),
+                    'b' | 'o' => {
+                        let run = if count == 0 { 1 } else { count };
+                        for _ in 0..run {
+                            self.set_cell_clamped(cur_row, cur_col, ch == 'o');
+                            cur_col += 1;
+                        }
+                        count = 0;
+                    }
+                    '$' => {
+                        let run = if count == 0 { 1 } else { count };
+                        cur_row += run;
+                        cur_col = col;
+                        count = 0;
+                    }
+                    '!' => break 'lines,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Encode the minimal bounding box of live cells as a Life RLE pattern.
+    pub fn to_rle(&self) -> String {
+        let mut bounds: Option<(u32, u32, u32, u32)> = None;
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                if !self.get_bit(idx) {
+                    continue;
+                }
+                bounds = Some(match bounds {
+                    None => (row, row, col, col),
+                    Some((min_row, max_row, min_col, max_col)) => (
+                        min_row.min(row),
+                        max_row.max(row),
+                        min_col.min(col),
+                        max_col.max(col),
+                    ),
+                });
+            }
+        }
+
+        let (min_row, max_row, min_col, max_col) = match bounds {
+            Some(bounds) => bounds,
+            None => return "x = 0, y = 0\n!\n".to_string(),
+        };
+
+        let mut body = String::new();
+        for row in min_row..=max_row {
+            let mut run_char = None;
+            let mut run_len = 0u32;
+            for col in min_col..=max_col {
+                let idx = self.get_index(row, col);
+                let c = if self.get_bit(idx) { 'o' } else { 'b' };
+                if Some(c) == run_char {
+                    run_len += 1;
+                } else {
+                    if let Some(prev) = run_char {
+                        push_run(&mut body, run_len, prev);
+                    }
+                    run_char = Some(c);
+                    run_len = 1;
+                }
+            }
+            // A trailing dead run at the end of a row is omitted.
+            if let Some(prev) = run_char {
+                if prev != 'b' {
+                    push_run(&mut body, run_len, prev);
+                }
+            }
+            body.push('$');
+        }
+        if body.ends_with('$') {
+            body.pop();
+        }
+        body.push('!');
+
+        format!(
+            "x = {}, y = {}\n{}\n",
+            max_col - min_col + 1,
+            max_row - min_row + 1,
+            body
+        )
+    }
 
+    pub fn tick(&mut self) {
         for row in 0..self.height {
             for col in 0..self.width {
                 let idx = self.get_index(row, col);
-                let cell = self.cells[idx];
+                let cell = self.get_bit(idx);
                 let live_neighbors = self.live_neighbor_count(row, col);
 
                 // log!(
-                //     "cell[{}, {}] is initially {:?} and has {} live neighbors",
+                //     "cell[{}, {}] is initially {} and has {} live neighbors",
                 //     row,
                 //     col,
                 //     cell,
                 //     live_neighbors
                 // );
 
-                let next_cell = match (cell, live_neighbors) {
-                    // Rule 1: Any live cell with fewer than two live
-                    // neighbors dies, as if caused by underpopulation
-                    (Cell::Alive, x) if x < 2 => Cell::Dead,
-                    // Rule 2: Any live cell with two or three live
-                    // neighbors lives on to the next generation
-                    (Cell::Alive, 2) | (Cell::Alive, 3) => Cell::Alive,
-                    // Rule 3: Any live cell with more than three live
-                    // neighbors dies, as if by overpopulation
-                    (Cell::Alive, x) if x > 3 => Cell::Dead,
-                    // Rule 4: Any dead cell with exactly three live
-                    // neighbors becomes a live cell, as if by repreoduction
-                    (Cell::Dead, 3) => Cell::Alive,
-                    // All other cells remain in same state
-                    (otherwise, _) => otherwise,
+                let mask = if cell {
+                    self.survival_rules
+                } else {
+                    self.birth_rules
                 };
+                let next_alive = mask & (1u16 << live_neighbors) != 0;
 
-                // log!("    it becomes {:?}", next_cell);
+                // log!("    it becomes {}", next_alive);
 
-                next[idx] = next_cell;
+                Universe::write_bit(&mut self.next_cells, idx, next_alive);
+
+                self.ages[idx] = if next_alive {
+                    0
+                } else {
+                    self.ages[idx].saturating_add(1)
+                };
             }
         }
 
-        self.cells = next;
+        std::mem::swap(&mut self.cells, &mut self.next_cells);
+    }
+
+    /// Run `tick` inside a `Timer` scope and fold its duration into
+    /// `avg_tick_micros`.
+    pub fn tick_timed(&mut self) {
+        let _timer = Timer::new("Universe::tick");
+        let start = now();
+        self.tick();
+        let elapsed_micros = (now() - start) * 1000.0;
+
+        self.total_tick_micros += elapsed_micros;
+        self.tick_count += 1;
+    }
+
+    /// Average `tick_timed` duration in microseconds, or `0.0` before the first.
+    pub fn avg_tick_micros(&self) -> f64 {
+        if self.tick_count == 0 {
+            0.0
+        } else {
+            self.total_tick_micros / f64::from(self.tick_count)
+        }
     }
 
     pub fn render(&self) -> String {
@@ -248,9 +530,10 @@ impl Universe {
 
 impl fmt::Display for Universe {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for line in self.cells.as_slice().chunks(self.width as usize) {
-            for &cell in line {
-                let symbol = if cell == Cell::Dead { '◻' } else { '◼' };
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let idx = self.get_index(row, col);
+                let symbol = if self.get_bit(idx) { '◼' } else { '◻' };
                 write!(f, "{}", symbol)?;
             }
             write!(f, "\n")?;